@@ -1,13 +1,14 @@
 use anyhow::Result;
 use editor::{scroll::Autoscroll, Editor};
 use gpui::{
-    div, list, prelude::*, uniform_list, AnyElement, AppContext, ClickEvent, DismissEvent,
-    EventEmitter, FocusHandle, FocusableView, Length, ListState, Render, Task,
-    UniformListScrollHandle, View, ViewContext, WindowContext,
+    canvas, div, list, prelude::*, uniform_list, AbsoluteLength, AnyElement, AnyView, AppContext,
+    ClickEvent, DefiniteLength, DismissEvent, EntityId, EventEmitter, FocusHandle, FocusableView,
+    Length, ListState, Pixels, Render, Task, UniformListScrollHandle, View, ViewContext,
+    WindowContext,
 };
 use head::Head;
 use search::SearchOptions;
-use std::{sync::Arc, time::Duration};
+use std::{cell::Cell, path::Path, rc::Rc, sync::Arc, time::Duration};
 use ui::{prelude::*, v_flex, Color, Divider, Label, ListItem, ListItemSpacing};
 use workspace::ModalView;
 
@@ -33,12 +34,79 @@ pub struct Picker<D: PickerDelegate> {
     width: Option<Length>,
     max_height: Option<Length>,
 
+    /// The minimum available width at which the preview pane (if the delegate has one) is shown.
+    ///
+    /// Below this width, the preview is dropped in favor of giving the match list all the room.
+    min_preview_width: Option<Pixels>,
+
+    /// The picker's own on-screen width, as last measured by a `canvas` in `render` rather than
+    /// proxied through the window's viewport. Most pickers (especially full-screen modals, which
+    /// size themselves via the surrounding modal chrome rather than `Picker::width`) never set an
+    /// explicit `width`, so falling back to the viewport would treat "unset" as "assume the whole
+    /// window," gating the preview on a much larger box than the picker actually occupies.
+    measured_width: Rc<Cell<Pixels>>,
+
+    /// Memoized preview content, so moving the selection back to a previously-seen match doesn't
+    /// re-render (and for file-backed previews, re-read from disk) it. Keyed by `PreviewTarget`
+    /// rather than just `PreviewKey` so that two matches in the same file but at different line
+    /// ranges don't share a cache entry.
+    ///
+    /// Entries are `AnyView`, not `AnyElement`: views are the persistent, reference-counted
+    /// handles gpui is built around retaining across frames (see `editor.clone()` on the
+    /// `Head::Editor` below), whereas an `AnyElement` is one-shot layout/paint state that isn't
+    /// meant to survive past the frame it was built for. A cache hit moves the entry to the back
+    /// of the list, so eviction (when over `PREVIEW_CACHE_CAPACITY`) is actually LRU rather than
+    /// insertion-order FIFO.
+    preview_cache: Vec<(PreviewTarget, AnyView)>,
+    max_preview_bytes: u64,
+
     /// Whether the `Picker` is rendered as a self-contained modal.
     ///
     /// Set this to `false` when rendering the `Picker` as part of a larger modal.
     is_modal: bool,
 }
 
+/// The default minimum width, in rems, above which the preview pane is shown. The number is the
+/// column count Helix gates its own preview on, but it is applied here as rems, not columns.
+const DEFAULT_MIN_PREVIEW_WIDTH: f32 = 72.;
+
+/// The default cap on previewable file size, matching Helix's own preview size guard.
+const DEFAULT_MAX_PREVIEW_BYTES: u64 = 10 * 1024 * 1024;
+
+/// How many entries `Picker` keeps warm in its preview cache before evicting the least recently
+/// used one.
+const PREVIEW_CACHE_CAPACITY: usize = 32;
+
+/// Identifies the item a preview was rendered for, so `Picker` can memoize it across selection
+/// changes. Delegates back this with whatever they already use to identify an item uniquely,
+/// e.g. a worktree-relative path or an open buffer's id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PreviewKey {
+    Path(Arc<Path>),
+    Buffer(EntityId),
+}
+
+/// An item a preview should point at, plus an optional inclusive `(start_line, end_line)` range
+/// within it that the preview should scroll to and highlight. Lines are 0-indexed. A `None`
+/// range means show the top of the content, as with a plain file or buffer preview.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PreviewTarget {
+    pub key: PreviewKey,
+    pub line_range: Option<(u32, u32)>,
+}
+
+/// Computes the row to scroll a preview to so that `line_range` is centered within a viewport of
+/// `visible_lines`, clamping so the viewport doesn't run past the start or end of a file that is
+/// `total_lines` long. Delegates whose `render_preview` highlights a `PreviewTarget`'s line range
+/// can use this to position their embedded editor's scroll anchor.
+pub fn centered_scroll_anchor(line_range: (u32, u32), visible_lines: u32, total_lines: u32) -> u32 {
+    let center = line_range.0 + (line_range.1.saturating_sub(line_range.0)) / 2;
+    let half_viewport = visible_lines / 2;
+    let anchor = center.saturating_sub(half_viewport);
+    let max_anchor = total_lines.saturating_sub(visible_lines.min(total_lines));
+    anchor.min(max_anchor)
+}
+
 #[derive(Copy, Clone)]
 pub struct SupportedSearchOptions {
     include_ignored: bool,
@@ -103,6 +171,53 @@ pub trait PickerDelegate: Sized + 'static {
     fn render_footer(&self, _: &mut ViewContext<Picker<Self>>) -> Option<AnyElement> {
         None
     }
+
+    /// Renders a preview of the item at `ix`, shown alongside the match list.
+    ///
+    /// Returning `None` (the default) means this delegate has no preview to show, and the
+    /// `Picker` falls back to its single-column layout. The `Picker` caches the returned view
+    /// by `preview_location`, so this is only invoked on a cache miss, not on every selection
+    /// change; it's still worth keeping cheap, since a miss happens whenever the selection moves
+    /// to an item whose preview isn't already in the cache.
+    ///
+    /// `line_range` is the inclusive, 0-indexed `(start_line, end_line)` `PreviewTarget` wants
+    /// highlighted (see `preview_location`), or `None` to just show the top of the content.
+    /// Implementations that target a specific place in a file should scroll to and highlight
+    /// this range; `centered_scroll_anchor` computes a centered, edge-clamped scroll position
+    /// given the range, the preview's visible line count, and the content's total line count.
+    fn render_preview(
+        &self,
+        _ix: usize,
+        _line_range: Option<(u32, u32)>,
+        _cx: &mut ViewContext<Picker<Self>>,
+    ) -> Option<AnyView> {
+        None
+    }
+
+    /// Identifies the item at `ix` so `Picker` can cache its rendered preview. The cache is keyed
+    /// by `preview_location`, which defaults to this key; with that default, returning `None`
+    /// (the default) opts the item out of caching, and its preview is rendered fresh every time.
+    fn preview_key(&self, _ix: usize) -> Option<PreviewKey> {
+        None
+    }
+
+    /// Locates what the preview for `ix` should point at: the item's identity plus, for
+    /// pickers that target a specific place in a file (search results, go-to-symbol,
+    /// diagnostics), the line range to scroll to and highlight. Defaults to `preview_key`'s
+    /// item with no range, i.e. showing the top of the content.
+    fn preview_location(&self, ix: usize) -> Option<PreviewTarget> {
+        self.preview_key(ix).map(|key| PreviewTarget {
+            key,
+            line_range: None,
+        })
+    }
+
+    /// Reports the on-disk size of the item at `ix`'s preview content, if known. When this
+    /// exceeds `Picker`'s configured `max_preview_bytes`, the picker shows a placeholder instead
+    /// of calling `render_preview`, so delegates don't need their own size guard.
+    fn preview_size_bytes(&self, _ix: usize) -> Option<u64> {
+        None
+    }
 }
 
 impl<D: PickerDelegate> FocusableView for Picker<D> {
@@ -164,6 +279,10 @@ impl<D: PickerDelegate> Picker<D> {
             confirm_on_update: None,
             width: None,
             max_height: None,
+            min_preview_width: None,
+            measured_width: Rc::new(Cell::new(px(0.))),
+            preview_cache: Vec::new(),
+            max_preview_bytes: DEFAULT_MAX_PREVIEW_BYTES,
             is_modal: true,
         };
         this.update_matches("".to_string(), cx);
@@ -211,11 +330,45 @@ impl<D: PickerDelegate> Picker<D> {
         self
     }
 
+    /// Sets the minimum available width at which the preview pane is shown. Below this width,
+    /// the preview is hidden and the match list uses the full width instead.
+    pub fn min_preview_width(mut self, min_preview_width: impl Into<Pixels>) -> Self {
+        self.min_preview_width = Some(min_preview_width.into());
+        self
+    }
+
+    /// Sets the maximum size, in bytes, of content `Picker` will render a preview for. Items
+    /// whose `PickerDelegate::preview_size_bytes` exceeds this show a placeholder instead.
+    pub fn max_preview_bytes(mut self, max_preview_bytes: u64) -> Self {
+        self.max_preview_bytes = max_preview_bytes;
+        self
+    }
+
     pub fn modal(mut self, modal: bool) -> Self {
         self.is_modal = modal;
         self
     }
 
+    /// The width actually available to this picker, used to decide whether there's room for a
+    /// preview pane. Respects an explicit absolute `.width(...)` (pixels or rems); otherwise
+    /// falls back to `measured_width`, the picker's own on-screen box as captured by a `canvas`
+    /// in `render` on the previous frame. Most pickers — especially full-screen modals, which
+    /// size themselves via the surrounding modal chrome rather than `Picker::width` — never set
+    /// an explicit width, so proxying through the window's viewport there would gate the preview
+    /// on a box far larger than what the picker actually occupies (e.g. a ~540px modal on a
+    /// 1440px window).
+    fn available_width(&self, cx: &mut ViewContext<Self>) -> Pixels {
+        match self.width {
+            Some(Length::Definite(DefiniteLength::Absolute(AbsoluteLength::Pixels(width)))) => {
+                width
+            }
+            Some(Length::Definite(DefiniteLength::Absolute(AbsoluteLength::Rems(width)))) => {
+                width.to_pixels(cx.rem_size())
+            }
+            _ => self.measured_width.get(),
+        }
+    }
+
     pub fn focus(&self, cx: &mut WindowContext) {
         self.focus_handle(cx).focus(cx);
     }
@@ -372,6 +525,9 @@ impl<D: PickerDelegate> Picker<D> {
         if let ElementContainer::List(state) = &mut self.element_container {
             state.reset(self.delegate.match_count());
         }
+        // The result set changed, so cached previews may no longer correspond to the items at
+        // those indices (or to items that exist at all anymore).
+        self.preview_cache.clear();
 
         let index = self.delegate.selected_index();
         self.scroll_to_item_index(index);
@@ -452,6 +608,50 @@ impl<D: PickerDelegate> Picker<D> {
         }
     }
 
+    /// Renders the preview for `ix`, going through the preview cache (keyed by `PreviewTarget`,
+    /// so distinct line ranges within the same item don't collide) and the `max_preview_bytes`
+    /// size guard before falling back to `PickerDelegate::render_preview`.
+    fn render_cached_preview(&mut self, ix: usize, cx: &mut ViewContext<Self>) -> Option<AnyElement> {
+        if let Some(size) = self.delegate.preview_size_bytes(ix) {
+            if size > self.max_preview_bytes {
+                return Some(self.render_preview_too_large(cx));
+            }
+        }
+
+        let Some(target) = self.delegate.preview_location(ix) else {
+            return self
+                .delegate
+                .render_preview(ix, None, cx)
+                .map(IntoElement::into_any_element);
+        };
+
+        if let Some(position) = self.preview_cache.iter().position(|(t, _)| t == &target) {
+            // Touch the entry: move it to the back so it isn't the next thing evicted.
+            let (target, view) = self.preview_cache.remove(position);
+            let element = view.clone().into_any_element();
+            self.preview_cache.push((target, view));
+            return Some(element);
+        }
+
+        let view = self.delegate.render_preview(ix, target.line_range, cx)?;
+        let element = view.clone().into_any_element();
+        self.preview_cache.push((target, view));
+        if self.preview_cache.len() > PREVIEW_CACHE_CAPACITY {
+            self.preview_cache.remove(0);
+        }
+        Some(element)
+    }
+
+    fn render_preview_too_large(&self, cx: &mut ViewContext<Self>) -> AnyElement {
+        v_flex()
+            .size_full()
+            .items_center()
+            .justify_center()
+            .bg(cx.theme().colors().surface_background)
+            .child(Label::new("File too large to preview").color(Color::Muted))
+            .into_any_element()
+    }
+
     fn render_search_buttons(&self, cx: &mut ViewContext<Self>) -> Vec<impl IntoElement> {
         let mut buttons = vec![];
         if self.delegate.supported_search_options().include_ignored {
@@ -477,6 +677,7 @@ impl<D: PickerDelegate> ModalView for Picker<D> {}
 
 impl<D: PickerDelegate> Render for Picker<D> {
     fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let measured_width = self.measured_width.clone();
         div()
             .key_context("Picker")
             .size_full()
@@ -487,6 +688,19 @@ impl<D: PickerDelegate> Render for Picker<D> {
             //
             // We should revisit how the `Picker` is styled to make it more composable.
             .when(self.is_modal, |this| this.elevation_3(cx))
+            // Measures the picker's own laid-out width into `measured_width` so
+            // `available_width` can gate the preview pane on the box the picker actually
+            // occupies instead of the window's viewport. The `canvas` has no visual output and
+            // is positioned absolutely so it doesn't affect the rest of the layout; the
+            // measurement lags one frame behind resizes, which is fine for this gate.
+            .child(
+                canvas(
+                    move |bounds, _cx| measured_width.set(bounds.size.width),
+                    |_, _, _| {},
+                )
+                .absolute()
+                .size_full(),
+            )
             .on_action(cx.listener(Self::select_next))
             .on_action(cx.listener(Self::select_prev))
             .on_action(cx.listener(Self::select_first))
@@ -510,14 +724,39 @@ impl<D: PickerDelegate> Render for Picker<D> {
                 Head::Empty(empty_head) => div().child(empty_head.clone()),
             })
             .when(self.delegate.match_count() > 0, |el| {
-                el.child(
-                    v_flex()
-                        .flex_grow()
-                        .max_h(self.max_height.unwrap_or(rems(18.).into()))
-                        .overflow_hidden()
-                        .children(self.delegate.render_header(cx))
-                        .child(self.render_element_container(cx)),
-                )
+                let match_list = v_flex()
+                    .flex_grow()
+                    .max_h(self.max_height.unwrap_or(rems(18.).into()))
+                    .overflow_hidden()
+                    .children(self.delegate.render_header(cx))
+                    .child(self.render_element_container(cx));
+
+                let min_preview_width = self
+                    .min_preview_width
+                    .unwrap_or_else(|| rems(DEFAULT_MIN_PREVIEW_WIDTH).to_pixels(cx.rem_size()));
+                let has_room_for_preview = self.available_width(cx) > min_preview_width;
+
+                match has_room_for_preview
+                    .then(|| self.render_cached_preview(self.delegate.selected_index(), cx))
+                    .flatten()
+                {
+                    Some(preview) => el.child(
+                        h_flex()
+                            .flex_grow()
+                            .overflow_hidden()
+                            .child(match_list.w_1_2())
+                            .child(
+                                v_flex()
+                                    .w_1_2()
+                                    .max_h(self.max_height.unwrap_or(rems(18.).into()))
+                                    .overflow_hidden()
+                                    .border_l_1()
+                                    .border_color(cx.theme().colors().border_variant)
+                                    .child(preview),
+                            ),
+                    ),
+                    None => el.child(match_list),
+                }
             })
             .when(self.delegate.match_count() == 0, |el| {
                 el.child(
@@ -533,3 +772,32 @@ impl<D: PickerDelegate> Render for Picker<D> {
             .children(self.delegate.render_footer(cx))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn centers_a_range_in_the_middle_of_the_file() {
+        assert_eq!(centered_scroll_anchor((100, 100), 20, 1000), 90);
+    }
+
+    #[test]
+    fn clamps_when_the_range_is_near_the_start_of_the_file() {
+        // Centering (5, 5) in a 20-line viewport would ask for a negative anchor; it should
+        // clamp to the top instead.
+        assert_eq!(centered_scroll_anchor((5, 5), 20, 1000), 0);
+    }
+
+    #[test]
+    fn clamps_when_the_range_is_near_the_end_of_the_file() {
+        // Centering (995, 998) in a 20-line viewport would scroll past line 1000; it should
+        // clamp so the viewport still ends exactly at the last line.
+        assert_eq!(centered_scroll_anchor((995, 998), 20, 1000), 980);
+    }
+
+    #[test]
+    fn clamps_when_the_whole_file_is_shorter_than_the_viewport() {
+        assert_eq!(centered_scroll_anchor((2, 4), 20, 10), 0);
+    }
+}